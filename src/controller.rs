@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use std::ptr;
+use winapi::shared::windef::HWND;
+use winapi::um::wingdi::RGB;
+use winapi::um::winuser::{DestroyWindow, SetLayeredWindowAttributes, ShowWindow, SW_HIDE};
+
+use crate::monitor::{self, MonitorState};
+
+/// Whether the dim overlay is drawn per-monitor or as a single window
+/// spanning the whole virtual desktop.
+pub enum OverlayMode {
+    PerMonitor,
+    Spanning,
+}
+
+/// Owns the detected monitors and their dimmer overlays and applies
+/// brightness/dim changes to them. Shared by the GUI (`LuxFlex`) and the
+/// headless CLI path so neither duplicates the other's control logic.
+pub struct Controller {
+    pub monitors: Vec<MonitorState>,
+    pub overlay_mode: OverlayMode,
+    pub spanning_dimmer_hwnd: HWND,
+    pub spanning_dimmer_alpha: u8,
+}
+
+impl Controller {
+    /// Enumerates the attached monitors and creates their dimmer overlays.
+    /// Used by the GUI, which always has an interactive window station.
+    pub fn new() -> Result<Self> {
+        let mut monitors = monitor::enumerate_monitors()?;
+        for monitor in &mut monitors {
+            monitor.create_dimmer_window()?;
+        }
+        Ok(Self::from_monitors(monitors))
+    }
+
+    /// Enumerates the attached monitors without creating any overlay windows.
+    /// Used by the headless CLI path, which may run without an interactive
+    /// window station (e.g. a Task Scheduler job in Session 0) and should be
+    /// able to set brightness without ever touching `CreateWindowExW`.
+    /// Dimmer windows are created lazily, the first time a non-zero dim level
+    /// is set.
+    pub fn new_headless() -> Result<Self> {
+        Ok(Self::from_monitors(monitor::enumerate_monitors()?))
+    }
+
+    fn from_monitors(monitors: Vec<MonitorState>) -> Self {
+        Self {
+            monitors,
+            overlay_mode: OverlayMode::PerMonitor,
+            spanning_dimmer_hwnd: ptr::null_mut(),
+            spanning_dimmer_alpha: 0,
+        }
+    }
+
+    pub fn monitor_count(&self) -> usize {
+        self.monitors.len()
+    }
+
+    /// Applies the combined brightness/dim slider formula: 0-50 ramps
+    /// brightness down to 0%, 50-100 ramps the dim overlay up instead.
+    pub fn update_from_slider(&mut self, index: usize, value: i32) -> Result<()> {
+        let alpha = if value <= 50 {
+            self.monitor_mut(index)?.set_brightness((value * 2) as u8)?;
+            0
+        } else {
+            self.monitor_mut(index)?.set_brightness(100)?;
+            ((value - 50) * 5) as u8
+        };
+        self.apply_dimmer(index, alpha)
+    }
+
+    /// Sets a monitor's brightness directly (0-100), independent of dimming.
+    pub fn set_brightness(&mut self, index: usize, brightness: u8) -> Result<()> {
+        self.monitor_mut(index)?.set_brightness(brightness)
+    }
+
+    /// Sets a monitor's (or the spanning overlay's) dim level directly (0-100 alpha).
+    pub fn set_dimmer(&mut self, index: usize, alpha: u8) -> Result<()> {
+        self.apply_dimmer(index, alpha)
+    }
+
+    fn apply_dimmer(&mut self, index: usize, alpha: u8) -> Result<()> {
+        match self.overlay_mode {
+            OverlayMode::PerMonitor => {
+                let monitor = self.monitor_mut(index)?;
+                if monitor.dimmer_hwnd.is_null() {
+                    // No dimming requested yet: an overlay that doesn't exist
+                    // is already fully transparent, so there's nothing to do.
+                    if alpha == 0 {
+                        monitor.dimmer_alpha = 0;
+                        return Ok(());
+                    }
+                    monitor.create_dimmer_window()?;
+                }
+                monitor.set_dimmer(alpha)
+            }
+            OverlayMode::Spanning => {
+                self.set_spanning_dimmer(alpha);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets the single overlay's dim level directly, ignoring which monitor's
+    /// slider asked for it: in spanning mode there's one shared window, so
+    /// the caller (the UI) is expected to show one shared control for it
+    /// rather than a slider per monitor.
+    pub fn set_spanning_dimmer(&mut self, alpha: u8) {
+        unsafe {
+            SetLayeredWindowAttributes(self.spanning_dimmer_hwnd, RGB(0, 0, 0), alpha, 2);
+        }
+        self.spanning_dimmer_alpha = alpha;
+    }
+
+    /// Switches between per-monitor overlays and a single overlay spanning
+    /// the whole virtual desktop, tearing down/creating windows as needed.
+    pub fn toggle_overlay_mode(&mut self) -> Result<()> {
+        match self.overlay_mode {
+            OverlayMode::PerMonitor => {
+                let hwnd = monitor::create_overlay_window(&monitor::virtual_desktop_rect())?;
+                for monitor in &self.monitors {
+                    unsafe {
+                        ShowWindow(monitor.dimmer_hwnd, SW_HIDE);
+                    }
+                }
+                self.spanning_dimmer_hwnd = hwnd;
+                self.spanning_dimmer_alpha = 0;
+                self.overlay_mode = OverlayMode::Spanning;
+            }
+            OverlayMode::Spanning => {
+                unsafe {
+                    DestroyWindow(self.spanning_dimmer_hwnd);
+                }
+                self.spanning_dimmer_hwnd = ptr::null_mut();
+                self.overlay_mode = OverlayMode::PerMonitor;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-enumerates monitors after a display change, reconciling overlays,
+    /// and recreates the spanning overlay if that mode is active.
+    pub fn refresh_monitors(&mut self) -> Result<()> {
+        let monitors = std::mem::take(&mut self.monitors);
+        self.monitors = monitor::refresh_monitors(monitors)?;
+
+        if let OverlayMode::Spanning = self.overlay_mode {
+            for monitor in &self.monitors {
+                unsafe {
+                    ShowWindow(monitor.dimmer_hwnd, SW_HIDE);
+                }
+            }
+            unsafe {
+                DestroyWindow(self.spanning_dimmer_hwnd);
+            }
+            self.spanning_dimmer_hwnd =
+                monitor::create_overlay_window(&monitor::virtual_desktop_rect())?;
+        }
+        Ok(())
+    }
+
+    /// The overlay window(s) that should currently be shown/hidden together.
+    pub fn overlay_hwnds(&self) -> Vec<HWND> {
+        match self.overlay_mode {
+            OverlayMode::PerMonitor => self.monitors.iter().map(|m| m.dimmer_hwnd).collect(),
+            OverlayMode::Spanning => vec![self.spanning_dimmer_hwnd],
+        }
+    }
+
+    fn monitor_mut(&mut self, index: usize) -> Result<&mut MonitorState> {
+        self.monitors
+            .get_mut(index)
+            .context("Monitor index out of range for detected monitors")
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use crossbeam_channel::Sender;
+use std::ptr;
+use std::thread;
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, GetWindowLongPtrW,
+    RegisterClassExW, SetWindowLongPtrW, ShowWindow, TranslateMessage, GWLP_USERDATA, MSG, SW_HIDE,
+    WM_DISPLAYCHANGE, WM_DPICHANGED, WNDCLASSEXW, WS_OVERLAPPED,
+};
+
+use crate::SystrayMessage;
+
+/// Spawns a hidden top-level window that listens for `WM_DISPLAYCHANGE` and
+/// `WM_DPICHANGED`, forwarding `SystrayMessage::DisplaysChanged` through `tx`
+/// whenever the display layout might have changed. The window must be a real
+/// top-level window (not message-only): `WM_DISPLAYCHANGE` is broadcast only
+/// to top-level windows, and a message-only window has no monitor to report
+/// `WM_DPICHANGED` for.
+pub fn spawn(tx: Sender<SystrayMessage>) -> Result<()> {
+    thread::spawn(move || {
+        if let Err(err) = run(tx) {
+            eprintln!("display watcher exited: {:?}", err);
+        }
+    });
+    Ok(())
+}
+
+fn run(tx: Sender<SystrayMessage>) -> Result<()> {
+    unsafe {
+        let class_name = wide_string("LuxFlexDisplayWatcher");
+        let hinstance = GetModuleHandleW(ptr::null());
+
+        let wndclass = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: 0,
+            lpfnWndProc: Some(wndproc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: hinstance,
+            hIcon: ptr::null_mut(),
+            hCursor: ptr::null_mut(),
+            hbrBackground: ptr::null_mut(),
+            lpszMenuName: ptr::null(),
+            lpszClassName: class_name.as_ptr(),
+            hIconSm: ptr::null_mut(),
+        };
+        RegisterClassExW(&wndclass);
+
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            hinstance,
+            ptr::null_mut(),
+        );
+
+        if hwnd.is_null() {
+            anyhow::bail!("Failed to create display-watcher window");
+        }
+
+        // Never shown: this window only exists to receive WM_DISPLAYCHANGE/WM_DPICHANGED.
+        ShowWindow(hwnd, SW_HIDE);
+
+        let sender = Box::into_raw(Box::new(tx));
+        SetWindowLongPtrW(hwnd, GWLP_USERDATA, sender as isize);
+
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        drop(Box::from_raw(sender));
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn wndproc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if msg == WM_DISPLAYCHANGE || msg == WM_DPICHANGED {
+        let sender = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const Sender<SystrayMessage>;
+        if let Some(sender) = sender.as_ref() {
+            let _ = sender.send(SystrayMessage::DisplaysChanged);
+        }
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
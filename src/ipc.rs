@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use crossbeam_channel::Sender;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::ptr;
+use std::thread;
+use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+use winapi::um::winbase::{PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_WAIT};
+
+use crate::SystrayMessage;
+
+const PIPE_NAME: &str = r"\\.\pipe\luxflex";
+const BUFFER_SIZE: u32 = 4096;
+
+/// Spawns a named-pipe IPC server so other processes (keyboard-macro daemons,
+/// ambient-light sensors, ...) can drive brightness/dim/visibility without
+/// touching the UI. Accepted commands are forwarded into the existing
+/// crossbeam channel as `SystrayMessage` variants, flowing through the same
+/// `subscription`/`update` path the sliders use.
+pub fn spawn(tx: Sender<SystrayMessage>) -> Result<()> {
+    thread::spawn(move || loop {
+        if let Err(err) = accept_and_serve(&tx) {
+            eprintln!("ipc server error: {:?}", err);
+        }
+    });
+    Ok(())
+}
+
+fn accept_and_serve(tx: &Sender<SystrayMessage>) -> Result<()> {
+    let name = wide_string(PIPE_NAME);
+
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+            1,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            ptr::null_mut(),
+        )
+    };
+
+    if pipe == INVALID_HANDLE_VALUE {
+        anyhow::bail!("Failed to create IPC pipe");
+    }
+
+    let connected = unsafe {
+        ConnectNamedPipe(pipe, ptr::null_mut()) != 0 || GetLastError() == ERROR_PIPE_CONNECTED
+    };
+
+    if !connected {
+        unsafe {
+            CloseHandle(pipe);
+        }
+        anyhow::bail!("Failed to connect IPC pipe");
+    }
+
+    let file = unsafe { File::from_raw_handle(pipe as RawHandle) };
+    for line in BufReader::new(file).lines() {
+        let line = line.context("Failed to read IPC command")?;
+        if let Err(err) = handle_command(&line, tx) {
+            eprintln!("ignoring invalid IPC command {:?}: {:?}", line, err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses one newline-terminated IPC command and forwards it as a
+/// `SystrayMessage`. Supported commands: `set_brightness <monitor> <0-100>`,
+/// `set_dim <monitor> <0-100>` (1-based monitor index), `show`, `hide`, `quit`.
+fn handle_command(line: &str, tx: &Sender<SystrayMessage>) -> Result<()> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some("set_brightness") => {
+            let index = parse_monitor_index(&mut tokens)?;
+            let value = parse_percentage(&mut tokens, "set_brightness value")?;
+            tx.send(SystrayMessage::SetBrightness(index, value))?;
+        }
+        Some("set_dim") => {
+            let index = parse_monitor_index(&mut tokens)?;
+            let value = parse_percentage(&mut tokens, "set_dim value")?;
+            tx.send(SystrayMessage::SetDim(index, value))?;
+        }
+        Some("show") => tx.send(SystrayMessage::Show)?,
+        Some("hide") => tx.send(SystrayMessage::Hide)?,
+        Some("quit") => tx.send(SystrayMessage::Quit)?,
+        Some(other) => anyhow::bail!("Unrecognized IPC command: {}", other),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Monitors are addressed 1-based over IPC (to match the headless CLI's
+/// `--monitor`); `0` is rejected rather than silently aliased to monitor 1.
+fn parse_monitor_index<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<usize> {
+    let one_based: usize = next_token(tokens, "monitor index")?.parse()?;
+    if one_based == 0 {
+        anyhow::bail!("monitor index is 1-based; 0 is not a valid monitor");
+    }
+    Ok(one_based - 1)
+}
+
+fn parse_percentage<'a>(tokens: &mut impl Iterator<Item = &'a str>, what: &str) -> Result<u8> {
+    let value: u8 = next_token(tokens, what)?.parse()?;
+    if value > 100 {
+        anyhow::bail!("{} must be between 0 and 100, got {}", what, value);
+    }
+    Ok(value)
+}
+
+fn next_token<'a>(tokens: &mut impl Iterator<Item = &'a str>, what: &str) -> Result<&'a str> {
+    tokens
+        .next()
+        .with_context(|| format!("IPC command missing {}", what))
+}
+
+fn wide_string(s: &str) -> Vec<u16> {
+    OsStr::new(s)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
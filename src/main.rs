@@ -1,35 +1,53 @@
-use anyhow::{Result, Context};
+use anyhow::{Context, Result};
 use crossbeam_channel::{unbounded, Receiver};
-use iced::{executor, Application, Command, Element, Settings, Slider, Column, Text};
-use std::{thread, sync::Arc};
+use iced::{executor, Application, Column, Command, Element, Settings, Slider, Text};
 use std::sync::Mutex;
+use std::{sync::Arc, thread};
 use systray::Application as SystrayApplication;
-use winapi::um::winuser::{EnumDisplayMonitors, CreateWindowExW, SetLayeredWindowAttributes, ShowWindow, SW_SHOW, SW_HIDE};
-use winapi::um::wingdi::RGB;
-use winapi::um::physicalmonitorenumerationapi::{GetPhysicalMonitorsFromHMONITOR, PHYSICAL_MONITOR};
-use winapi::um::highlevelmonitorconfigurationapi::SetMonitorBrightness;
-use winapi::shared::windef::{HMONITOR, HDC, LPRECT, HWND};
-use winapi::shared::minwindef::BOOL;
-use std::ptr;
+use winapi::um::winuser::{
+    ShowWindow, SetProcessDpiAwarenessContext, SW_HIDE, SW_SHOW,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+};
+
+mod controller;
+mod display_watcher;
+mod ipc;
+mod monitor;
+
+use controller::{Controller, OverlayMode};
 
 struct LuxFlex {
-    brightness: u8,
-    dimmer_alpha: u8,
-    dimmer_hwnd: HWND,
+    controller: Controller,
     receiver: Receiver<SystrayMessage>,
     window_visible: bool,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
-    SliderChanged(u8),
+    SliderChanged(usize, u8),
+    BrightnessChanged(usize, u8),
+    SpanningDimChanged(u8),
     ToggleVisibility,
+    SetVisibility(bool),
+    ToggleOverlayMode,
+    SetBrightness(usize, u8),
+    SetDim(usize, u8),
+    DisplaysChanged,
 }
 
+/// Commands injected into the UI from outside the `Application`: the
+/// systray menu, the display-change watcher, and the IPC server all forward
+/// through this channel and the matching `Message` variants below.
 #[derive(Debug, Clone)]
-enum SystrayMessage {
+pub(crate) enum SystrayMessage {
     ShowControls,
+    Show,
+    Hide,
+    ToggleOverlayMode,
+    SetBrightness(usize, u8),
+    SetDim(usize, u8),
     Quit,
+    DisplaysChanged,
 }
 
 impl iced::Application for LuxFlex {
@@ -38,14 +56,13 @@ impl iced::Application for LuxFlex {
     type Flags = Receiver<SystrayMessage>;
 
     fn new(flags: Self::Flags) -> (Self, Command<Message>) {
-        let mut app = Self {
-            brightness: 50.0,
-            dimmer_alpha: 0,
-            dimmer_hwnd: ptr::null_mut(),
+        let controller = Controller::new().expect("Failed to initialize monitor controller");
+
+        let app = Self {
+            controller,
             receiver: flags,
             window_visible: false,
         };
-        app.create_dimmer_window().expect("Failed to create dimmer window");
         (app, Command::none())
     }
 
@@ -55,30 +72,91 @@ impl iced::Application for LuxFlex {
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
-            Message::SliderChanged(value) => {
-                self.update_from_slider(value as i32).expect("Failed to update from slider");
+            Message::SliderChanged(index, value) => {
+                self.controller
+                    .update_from_slider(index, value as i32)
+                    .expect("Failed to update from slider");
+            }
+            Message::BrightnessChanged(index, value) => {
+                self.controller
+                    .set_brightness(index, value)
+                    .expect("Failed to set brightness");
+            }
+            Message::SpanningDimChanged(value) => {
+                self.controller.set_spanning_dimmer(value);
             }
             Message::ToggleVisibility => {
                 self.window_visible = !self.window_visible;
-                if self.window_visible {
-                    unsafe { ShowWindow(self.dimmer_hwnd, SW_SHOW); }
-                } else {
-                    unsafe { ShowWindow(self.dimmer_hwnd, SW_HIDE); }
+                self.show_overlays(self.window_visible);
+            }
+            Message::SetVisibility(visible) => {
+                self.window_visible = visible;
+                self.show_overlays(self.window_visible);
+            }
+            Message::ToggleOverlayMode => {
+                self.controller
+                    .toggle_overlay_mode()
+                    .expect("Failed to toggle overlay mode");
+                self.show_overlays(self.window_visible);
+            }
+            Message::SetBrightness(index, value) => {
+                // index/value arrive over the IPC pipe, which any local
+                // process can write to, so an out-of-range monitor index is
+                // an invalid command to ignore, not a reason to panic.
+                if let Err(err) = self.controller.set_brightness(index, value) {
+                    eprintln!("ignoring SetBrightness({}, {}): {:?}", index, value, err);
+                }
+            }
+            Message::SetDim(index, value) => {
+                if let Err(err) = self.controller.set_dimmer(index, value) {
+                    eprintln!("ignoring SetDim({}, {}): {:?}", index, value, err);
                 }
             }
+            Message::DisplaysChanged => {
+                self.controller
+                    .refresh_monitors()
+                    .expect("Failed to refresh monitors after a display change");
+                self.show_overlays(self.window_visible);
+            }
         }
         Command::none()
     }
 
     fn view(&mut self) -> Element<Message> {
-        Column::new()
-            .push(Text::new("Brightness/Dimness"))
-            .push(Slider::new(
-                0..=100,
-                self.brightness as u8,
-                Message::SliderChanged,
-            ))
-            .into()
+        let mut column = Column::new().push(Text::new("Brightness/Dimness"));
+        match self.controller.overlay_mode {
+            // One overlay per monitor: a single combined slider per monitor
+            // drives that monitor's own brightness and dim level together.
+            OverlayMode::PerMonitor => {
+                for (index, monitor) in self.controller.monitors.iter().enumerate() {
+                    column = column
+                        .push(Text::new(format!("Monitor {}", index + 1)))
+                        .push(Slider::new(0..=100, monitor.brightness, move |value| {
+                            Message::SliderChanged(index, value)
+                        }));
+                }
+            }
+            // One overlay spans every monitor, so dimming is necessarily
+            // shared: show it as a single slider instead of one per monitor
+            // clobbering the same window. Brightness is still per-monitor.
+            OverlayMode::Spanning => {
+                for (index, monitor) in self.controller.monitors.iter().enumerate() {
+                    column = column
+                        .push(Text::new(format!("Monitor {} brightness", index + 1)))
+                        .push(Slider::new(0..=100, monitor.brightness, move |value| {
+                            Message::BrightnessChanged(index, value)
+                        }));
+                }
+                column = column
+                    .push(Text::new("Dim (all displays)"))
+                    .push(Slider::new(
+                        0..=100,
+                        self.controller.spanning_dimmer_alpha,
+                        Message::SpanningDimChanged,
+                    ));
+            }
+        }
+        column.into()
     }
 
     fn subscription(&self) -> iced::Subscription<Message> {
@@ -89,6 +167,18 @@ impl iced::Application for LuxFlex {
                 if let Ok(msg) = receiver.recv() {
                     match msg {
                         SystrayMessage::ShowControls => Some((Message::ToggleVisibility, receiver)),
+                        SystrayMessage::Show => Some((Message::SetVisibility(true), receiver)),
+                        SystrayMessage::Hide => Some((Message::SetVisibility(false), receiver)),
+                        SystrayMessage::ToggleOverlayMode => {
+                            Some((Message::ToggleOverlayMode, receiver))
+                        }
+                        SystrayMessage::SetBrightness(index, value) => {
+                            Some((Message::SetBrightness(index, value), receiver))
+                        }
+                        SystrayMessage::SetDim(index, value) => {
+                            Some((Message::SetDim(index, value), receiver))
+                        }
+                        SystrayMessage::DisplaysChanged => Some((Message::DisplaysChanged, receiver)),
                         SystrayMessage::Quit => std::process::exit(0),
                     }
                 } else {
@@ -100,97 +190,144 @@ impl iced::Application for LuxFlex {
 }
 
 impl LuxFlex {
-    fn set_brightness(&mut self, brightness: u32) -> Result<()> {
-        unsafe {
-            EnumDisplayMonitors(ptr::null_mut(), ptr::null(), Some(enum_monitor), brightness as isize);
+    fn show_overlays(&self, visible: bool) {
+        let show_state = if visible { SW_SHOW } else { SW_HIDE };
+        for hwnd in self.controller.overlay_hwnds() {
+            unsafe {
+                ShowWindow(hwnd, show_state);
+            }
         }
-        self.brightness = brightness as f32;
-        Ok(())
     }
+}
+
+/// Drives the monitor controller from command-line flags instead of the
+/// GUI, for scripting and scheduled tasks: `--set <0-100>` applies the
+/// combined brightness/dim slider formula to every detected monitor, while
+/// `--monitor <n> --brightness <0-100> --dim <0-100>` (1-based monitor
+/// index) targets a single monitor directly.
+fn run_headless(args: &[String]) -> Result<()> {
+    let mut controller = Controller::new_headless().context("Failed to enumerate monitors")?;
+
+    let mut monitor_index: Option<usize> = None;
+    let mut brightness: Option<u8> = None;
+    let mut dim: Option<u8> = None;
+    let mut set_value: Option<i32> = None;
 
-    fn set_dimmer(&mut self, alpha: u8) -> Result<()> {
-        unsafe {
-            SetLayeredWindowAttributes(self.dimmer_hwnd, RGB(0, 0, 0), alpha, 2);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--set" => {
+                set_value = Some(parse_percentage(next_arg(&mut args, "--set")?, "--set")? as i32);
+            }
+            "--monitor" => {
+                monitor_index = Some(parse_monitor_index(next_arg(&mut args, "--monitor")?)?);
+            }
+            "--brightness" => {
+                brightness = Some(parse_percentage(
+                    next_arg(&mut args, "--brightness")?,
+                    "--brightness",
+                )?);
+            }
+            "--dim" => {
+                dim = Some(parse_percentage(next_arg(&mut args, "--dim")?, "--dim")?);
+            }
+            other => anyhow::bail!("Unrecognized headless argument: {}", other),
         }
-        self.dimmer_alpha = alpha;
-        Ok(())
     }
 
-    fn create_dimmer_window(&mut self) -> Result<()> {
-        use winapi::um::winuser::{WS_EX_LAYERED, WS_EX_TRANSPARENT, WS_EX_TOPMOST, WS_POPUP};
-        use winapi::um::winuser::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
-
-        unsafe {
-            self.dimmer_hwnd = CreateWindowExW(
-                WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST,
-                wide_string("Static").as_ptr(),
-                ptr::null(),
-                WS_POPUP,
-                0, 0, GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN),
-                ptr::null_mut(),
-                ptr::null_mut(),
-                ptr::null_mut(),
-                ptr::null_mut()
-            );
-
-            if self.dimmer_hwnd.is_null() {
-                anyhow::bail!("Failed to create dimmer window");
-            }
-
-            SetLayeredWindowAttributes(self.dimmer_hwnd, RGB(0, 0, 0), 0, 2);
-            ShowWindow(self.dimmer_hwnd, SW_HIDE);
+    if let Some(value) = set_value {
+        for index in 0..controller.monitor_count() {
+            controller.update_from_slider(index, value)?;
         }
-        Ok(())
     }
 
-    fn update_from_slider(&mut self, value: i32) -> Result<()> {
-        if value <= 50 {
-            self.set_brightness((value * 2) as u32)?;
-            self.set_dimmer(0)?;
-        } else {
-            self.set_brightness(100)?;
-            self.set_dimmer(((value - 50) * 5) as u8)?;
+    if brightness.is_some() || dim.is_some() {
+        let index = monitor_index.context("--brightness/--dim require --monitor <n>")?;
+        if let Some(brightness) = brightness {
+            controller.set_brightness(index, brightness)?;
+        }
+        if let Some(dim) = dim {
+            controller.set_dimmer(index, dim)?;
         }
-        Ok(())
     }
+
+    Ok(())
 }
 
-unsafe extern "system" fn enum_monitor(hmonitor: HMONITOR, _: HDC, _: LPRECT, brightness: isize) -> BOOL {
-    let mut physical_monitor = PHYSICAL_MONITOR::default();
-    let monitor_count = 1;
-    
-    if GetPhysicalMonitorsFromHMONITOR(hmonitor, monitor_count, &mut physical_monitor) != 0 {
-        SetMonitorBrightness(physical_monitor.hPhysicalMonitor, brightness as u32);
+fn next_arg<'a>(args: &mut std::slice::Iter<'a, String>, flag: &str) -> Result<&'a str> {
+    args.next()
+        .map(String::as_str)
+        .with_context(|| format!("{} requires a value", flag))
+}
+
+fn parse_percentage(raw: &str, flag: &str) -> Result<u8> {
+    let value: u8 = raw
+        .parse()
+        .with_context(|| format!("{} expects an integer 0-100", flag))?;
+    if value > 100 {
+        anyhow::bail!("{} expects an integer 0-100, got {}", flag, value);
     }
-    
-    1 // Continue enumeration
+    Ok(value)
 }
 
-fn wide_string(s: &str) -> Vec<u16> {
-    s.encode_utf16().chain(std::iter::once(0)).collect()
+/// `--monitor` is 1-based; `0` is rejected rather than silently aliased to
+/// monitor 1, matching the IPC server's `parse_monitor_index`.
+fn parse_monitor_index(raw: &str) -> Result<usize> {
+    let one_based: usize = raw
+        .parse()
+        .context("--monitor expects a 1-based integer")?;
+    if one_based == 0 {
+        anyhow::bail!("--monitor expects a 1-based integer; 0 is not a valid monitor");
+    }
+    Ok(one_based - 1)
 }
 
 fn main() -> Result<()> {
+    unsafe {
+        SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_headless(&args);
+    }
+
     let (tx, rx) = unbounded();
     let tx = Arc::new(Mutex::new(tx));
 
     thread::spawn(move || {
         let mut systray = SystrayApplication::new().expect("Failed to create systray app");
-        
+
         let tx_clone = Arc::clone(&tx);
-        systray.add_menu_item("Show/Hide Controls", move |_| {
-            tx_clone.lock().unwrap().send(SystrayMessage::ShowControls).unwrap();
-        }).unwrap();
+        systray
+            .add_menu_item("Show/Hide Controls", move |_| {
+                tx_clone.lock().unwrap().send(SystrayMessage::ShowControls).unwrap();
+            })
+            .unwrap();
 
         let tx_clone = Arc::clone(&tx);
-        systray.add_menu_item("Quit", move |_| {
-            tx_clone.lock().unwrap().send(SystrayMessage::Quit).unwrap();
-        }).unwrap();
+        systray
+            .add_menu_item("Single Overlay Mode", move |_| {
+                tx_clone.lock().unwrap().send(SystrayMessage::ToggleOverlayMode).unwrap();
+            })
+            .unwrap();
+
+        let tx_clone = Arc::clone(&tx);
+        systray
+            .add_menu_item("Quit", move |_| {
+                tx_clone.lock().unwrap().send(SystrayMessage::Quit).unwrap();
+            })
+            .unwrap();
 
         systray.wait_for_message().unwrap();
     });
 
+    display_watcher::spawn(tx.lock().unwrap().clone())
+        .context("Failed to start display watcher")?;
+
+    ipc::spawn(tx.lock().unwrap().clone()).context("Failed to start IPC server")?;
+
     LuxFlex::run(Settings::with_flags(rx)).context("Failed to run iced app")?;
 
     Ok(())
-}
\ No newline at end of file
+}
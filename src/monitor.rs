@@ -0,0 +1,221 @@
+use anyhow::Result;
+use std::ptr;
+use winapi::shared::minwindef::{BOOL, LPARAM};
+use winapi::shared::windef::{HDC, HMONITOR, HWND, LPRECT, RECT};
+use winapi::um::highlevelmonitorconfigurationapi::SetMonitorBrightness;
+use winapi::um::physicalmonitorenumerationapi::{
+    DestroyPhysicalMonitors, GetNumberOfPhysicalMonitorsFromHMONITOR,
+    GetPhysicalMonitorsFromHMONITOR, PHYSICAL_MONITOR,
+};
+use winapi::um::wingdi::RGB;
+use winapi::um::winuser::{
+    CreateWindowExW, DestroyWindow, EnumDisplayMonitors, GetSystemMetrics, MoveWindow,
+    SetLayeredWindowAttributes, ShowWindow, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SW_HIDE, WS_EX_LAYERED, WS_EX_TOPMOST,
+    WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+/// Per-display state: the physical monitor handles behind an `HMONITOR`, the
+/// dimmer overlay window covering it, and the last brightness/dim values applied.
+pub struct MonitorState {
+    pub hmonitor: HMONITOR,
+    pub rect: RECT,
+    pub physical_monitors: Vec<PHYSICAL_MONITOR>,
+    pub dimmer_hwnd: HWND,
+    pub brightness: u8,
+    pub dimmer_alpha: u8,
+}
+
+impl MonitorState {
+    /// Creates this monitor's layered overlay window, sized to its bounding
+    /// `rect` in device pixels (the process must already be per-monitor DPI aware).
+    pub fn create_dimmer_window(&mut self) -> Result<()> {
+        self.dimmer_hwnd = create_overlay_window(&self.rect)?;
+        Ok(())
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<()> {
+        unsafe {
+            for physical_monitor in &self.physical_monitors {
+                SetMonitorBrightness(physical_monitor.hPhysicalMonitor, brightness as u32);
+            }
+        }
+        self.brightness = brightness;
+        Ok(())
+    }
+
+    pub fn set_dimmer(&mut self, alpha: u8) -> Result<()> {
+        unsafe {
+            SetLayeredWindowAttributes(self.dimmer_hwnd, RGB(0, 0, 0), alpha, 2);
+        }
+        self.dimmer_alpha = alpha;
+        Ok(())
+    }
+
+    /// Moves/resizes this monitor's overlay to match its current `rect`,
+    /// used when a display changes resolution or position without disconnecting.
+    pub fn reposition_dimmer_window(&self) {
+        unsafe {
+            MoveWindow(
+                self.dimmer_hwnd,
+                self.rect.left,
+                self.rect.top,
+                self.rect.right - self.rect.left,
+                self.rect.bottom - self.rect.top,
+                1,
+            );
+        }
+    }
+
+    pub fn destroy_dimmer_window(&self) {
+        unsafe {
+            DestroyWindow(self.dimmer_hwnd);
+        }
+    }
+}
+
+/// Re-enumerates the connected monitors and reconciles them against the
+/// previous list: overlays are destroyed for monitors that vanished, created
+/// for newly attached ones, and repositioned/resized for survivors whose
+/// `rect` moved. Monitors are matched across the refresh by `HMONITOR`.
+pub fn refresh_monitors(previous: Vec<MonitorState>) -> Result<Vec<MonitorState>> {
+    let mut previous = previous;
+    let mut refreshed = enumerate_monitors()?;
+
+    for monitor in &mut refreshed {
+        if let Some(index) = previous
+            .iter()
+            .position(|existing| existing.hmonitor == monitor.hmonitor)
+        {
+            let mut existing = previous.remove(index);
+            monitor.dimmer_hwnd = existing.dimmer_hwnd;
+            monitor.brightness = existing.brightness;
+            monitor.dimmer_alpha = existing.dimmer_alpha;
+            let moved = monitor.rect.left != existing.rect.left
+                || monitor.rect.top != existing.rect.top
+                || monitor.rect.right != existing.rect.right
+                || monitor.rect.bottom != existing.rect.bottom;
+            if moved {
+                monitor.reposition_dimmer_window();
+            }
+            // The fresh enumeration already holds this monitor's physical handles;
+            // release the superseded ones instead of leaking them.
+            destroy_physical_monitors(&mut existing.physical_monitors);
+        } else {
+            monitor.create_dimmer_window()?;
+        }
+    }
+
+    for mut vanished in previous {
+        vanished.destroy_dimmer_window();
+        destroy_physical_monitors(&mut vanished.physical_monitors);
+    }
+
+    Ok(refreshed)
+}
+
+fn destroy_physical_monitors(physical_monitors: &mut Vec<PHYSICAL_MONITOR>) {
+    if physical_monitors.is_empty() {
+        return;
+    }
+    unsafe {
+        DestroyPhysicalMonitors(physical_monitors.len() as u32, physical_monitors.as_mut_ptr());
+    }
+}
+
+/// Enumerates every active `HMONITOR`, resolving each one's bounding rect and
+/// physical monitor handles into a fresh `MonitorState` (with no dimmer
+/// window created yet). An `HMONITOR` whose physical monitors cannot be
+/// retrieved (or that reports none) is skipped and gets no `MonitorState`.
+pub fn enumerate_monitors() -> Result<Vec<MonitorState>> {
+    let mut monitors: Vec<MonitorState> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            ptr::null_mut(),
+            ptr::null(),
+            Some(collect_monitor),
+            &mut monitors as *mut Vec<MonitorState> as LPARAM,
+        );
+    }
+    Ok(monitors)
+}
+
+unsafe extern "system" fn collect_monitor(
+    hmonitor: HMONITOR,
+    _: HDC,
+    rect: LPRECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data as *mut Vec<MonitorState>);
+
+    let mut count: u32 = 0;
+    if GetNumberOfPhysicalMonitorsFromHMONITOR(hmonitor, &mut count) == 0 || count == 0 {
+        return 1;
+    }
+
+    let mut physical_monitors = vec![PHYSICAL_MONITOR::default(); count as usize];
+    if GetPhysicalMonitorsFromHMONITOR(hmonitor, count, physical_monitors.as_mut_ptr()) == 0 {
+        return 1;
+    }
+
+    monitors.push(MonitorState {
+        hmonitor,
+        rect: *rect,
+        physical_monitors,
+        dimmer_hwnd: ptr::null_mut(),
+        brightness: 50,
+        dimmer_alpha: 0,
+    });
+
+    1 // Continue enumeration
+}
+
+/// Creates a layered, click-through overlay window covering `rect` (in device
+/// pixels) and returns its handle, hidden and fully transparent.
+pub fn create_overlay_window(rect: &RECT) -> Result<HWND> {
+    unsafe {
+        let hwnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST,
+            wide_string("Static").as_ptr(),
+            ptr::null(),
+            WS_POPUP,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+
+        if hwnd.is_null() {
+            anyhow::bail!("Failed to create dimmer window");
+        }
+
+        SetLayeredWindowAttributes(hwnd, RGB(0, 0, 0), 0, 2);
+        ShowWindow(hwnd, SW_HIDE);
+        Ok(hwnd)
+    }
+}
+
+/// Returns the bounding rect of the whole virtual desktop (the union of every
+/// attached display), for the "single overlay" mode that dims everything at once.
+pub fn virtual_desktop_rect() -> RECT {
+    unsafe {
+        let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+        let width = GetSystemMetrics(SM_CXVIRTUALSCREEN);
+        let height = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+        RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+}
+
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}